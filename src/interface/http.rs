@@ -0,0 +1,261 @@
+//! The HTTP interface for the database, built on [`axum`].
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Error, Result};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+
+use crate::raft::{ClusterStatus, LogEntry};
+use crate::{
+    raft_instance, Aggregate, Metric, MetricSelector, Metrical, RetentionPolicy,
+    WriteStatsSnapshot,
+};
+
+/// Start serving the HTTP interface on `0.0.0.0:8080`.
+pub async fn serve() -> Result<(), Error> {
+    let app = Router::new()
+        .route("/metric", post(add_metric))
+        .route("/metric/:name/:key", get(get_metrics))
+        .route("/metric/:name", delete(drop_metric))
+        .route("/batch", post(add_metrics_batch))
+        .route("/batch/query", post(get_metrics_batch))
+        .route("/metrics", get(render_prometheus))
+        .route("/retention", get(get_retention).post(set_retention))
+        .route("/compact", post(compact))
+        .route("/debug/stats", get(debug_stats))
+        .route("/cluster", get(cluster_status))
+        .route("/cluster/join", post(cluster_join))
+        .route("/cluster/leave", post(cluster_leave));
+
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Optional time-range bounds, and optional rollup parameters, accepted by
+/// the single-series query route.
+#[derive(Debug, Deserialize)]
+struct RangeQuery {
+    start: Option<u64>,
+    end: Option<u64>,
+    bucket_secs: Option<u64>,
+    agg: Option<Aggregate>,
+}
+
+async fn add_metric(Json(metric): Json<Metric>) -> Result<(), AppError> {
+    let instance = Metrical::get_instance();
+    raft_instance()
+        .propose(instance, LogEntry::AddMetric(metric))
+        .await?;
+    Ok(())
+}
+
+async fn get_metrics(
+    Path((name, key)): Path<(String, String)>,
+    Query(range): Query<RangeQuery>,
+) -> Result<Json<Vec<Metric>>, AppError> {
+    let instance = Metrical::get_instance();
+    let mut metrical = instance.write().await;
+    let metrics = match (range.start, range.end, range.bucket_secs) {
+        (Some(start), Some(end), Some(bucket_secs)) => metrical.get_metrics_aggregated(
+            &name,
+            &key,
+            start,
+            end,
+            bucket_secs,
+            range.agg.unwrap_or(Aggregate::Avg),
+        )?,
+        (Some(start), Some(end), None) => metrical.get_metrics_range(&name, &key, start, end)?,
+        _ => metrical.get_metrics(&name, &key)?,
+    };
+    Ok(Json(metrics))
+}
+
+/// Ingest a batch of metrics through a single [`rocksdb::WriteBatch`], so
+/// either all of them land or none do.
+async fn add_metrics_batch(Json(metrics): Json<Vec<Metric>>) -> Result<(), AppError> {
+    let instance = Metrical::get_instance();
+    raft_instance()
+        .propose(instance, LogEntry::BatchAdd(metrics))
+        .await?;
+    Ok(())
+}
+
+/// Drop a metric and its entire column family in `O(1)`.
+async fn drop_metric(Path(name): Path<String>) -> Result<(), AppError> {
+    let instance = Metrical::get_instance();
+    raft_instance()
+        .propose(instance, LogEntry::DropMetric(name))
+        .await?;
+    Ok(())
+}
+
+/// Resolve a batch of selectors under a single read snapshot, returning a map
+/// from each selector's `"{name}:{key}"` label to its matching points. The
+/// range is not part of the label, so selectors for the same series share
+/// one entry and the last such selector's points are the ones returned.
+async fn get_metrics_batch(
+    Json(selectors): Json<Vec<MetricSelector>>,
+) -> Result<Json<HashMap<String, Vec<Metric>>>, AppError> {
+    let instance = Metrical::get_instance();
+    let metrical = instance.read().await;
+    let results = metrical.get_metrics_batch(&selectors)?;
+    let by_label = results
+        .into_iter()
+        .map(|(selector, metrics)| (format!("{}:{}", selector.name, selector.key), metrics))
+        .collect();
+    Ok(Json(by_label))
+}
+
+/// Render the latest value of every stored series in the Prometheus text
+/// exposition format, so existing Prometheus/VictoriaMetrics scrapers can
+/// pull from Metrical directly.
+async fn render_prometheus() -> Result<String, AppError> {
+    let instance = Metrical::get_instance();
+    let metrical = instance.read().await;
+
+    let mut by_name: BTreeMap<String, Vec<(String, Metric)>> = BTreeMap::new();
+    for (name, key) in metrical.list_series() {
+        if let Some(metric) = metrical.get_latest(&name, &key)? {
+            by_name.entry(name).or_default().push((key, metric));
+        }
+    }
+
+    let mut out = String::new();
+    for (name, points) in by_name {
+        let sanitized = sanitize_metric_name(&name);
+        out.push_str(&format!("# TYPE {sanitized} gauge\n"));
+        for (key, metric) in points {
+            out.push_str(&format!(
+                "{sanitized}{{key=\"{}\"}} {} {}\n",
+                escape_label_value(&key),
+                metric.value,
+                metric.timestamp * 1000,
+            ));
+        }
+    }
+    Ok(out)
+}
+
+/// Sanitize a metric name to the `[a-zA-Z_:][a-zA-Z0-9_:]*` charset the
+/// Prometheus exposition format requires, replacing invalid characters with
+/// `_`.
+fn sanitize_metric_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for (i, c) in name.chars().enumerate() {
+        let valid = if i == 0 {
+            c.is_ascii_alphabetic() || c == '_' || c == ':'
+        } else {
+            c.is_ascii_alphanumeric() || c == '_' || c == ':'
+        };
+        out.push(if valid { c } else { '_' });
+    }
+    if out.is_empty() {
+        out.push('_');
+    }
+    out
+}
+
+/// Escape a label value per the Prometheus exposition format.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Body accepted by `POST /retention`. Omitting `metric` sets the global
+/// default; providing it sets an override for just that metric.
+#[derive(Debug, Deserialize)]
+struct RetentionUpdate {
+    metric: Option<String>,
+    max_age_secs: u64,
+}
+
+async fn get_retention() -> Result<Json<RetentionPolicy>, AppError> {
+    let instance = Metrical::get_instance();
+    let metrical = instance.read().await;
+    Ok(Json(metrical.retention_policy()))
+}
+
+async fn set_retention(Json(update): Json<RetentionUpdate>) -> Result<(), AppError> {
+    let instance = Metrical::get_instance();
+    let metrical = instance.read().await;
+    match update.metric {
+        Some(metric) => metrical.set_metric_retention(metric, update.max_age_secs),
+        None => metrical.set_default_retention(update.max_age_secs),
+    }
+    Ok(())
+}
+
+/// Manually trigger compaction across every metric, letting operators
+/// reclaim space from expired points on demand.
+async fn compact() -> Result<(), AppError> {
+    let instance = Metrical::get_instance();
+    let metrical = instance.read().await;
+    metrical.force_compact()?;
+    Ok(())
+}
+
+/// Operational write/commit stats, both returned directly and persisted as
+/// stored points under Metrical's reserved self-instrumentation metric.
+async fn debug_stats() -> Result<Json<WriteStatsSnapshot>, AppError> {
+    let instance = Metrical::get_instance();
+    let snapshot = instance.read().await.stats_snapshot();
+    raft_instance()
+        .propose(instance, LogEntry::PersistStats(snapshot))
+        .await?;
+    Ok(Json(snapshot))
+}
+
+/// Body accepted by `POST /cluster/join` and `/cluster/leave`.
+#[derive(Debug, Deserialize)]
+struct ClusterMembershipUpdate {
+    addr: String,
+}
+
+/// This node's id and locally known peers.
+async fn cluster_status() -> Json<ClusterStatus> {
+    Json(raft_instance().status())
+}
+
+/// Add a peer to this node's locally known membership list.
+///
+/// This only updates local bookkeeping for the `/cluster` status route; it
+/// does not run a membership-change consensus round or notify the peer,
+/// since there is no inter-node transport yet.
+async fn cluster_join(Json(update): Json<ClusterMembershipUpdate>) {
+    raft_instance().join(update.addr);
+}
+
+/// Remove a peer from this node's locally known membership list. See
+/// [`cluster_join`] for the same caveat about there being no transport yet.
+async fn cluster_leave(Json(update): Json<ClusterMembershipUpdate>) {
+    raft_instance().leave(&update.addr);
+}
+
+/// Wraps [`anyhow::Error`] so handlers can use `?` and still produce a
+/// `500` JSON response instead of panicking.
+struct AppError(Error);
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        (StatusCode::INTERNAL_SERVER_ERROR, self.0.to_string()).into_response()
+    }
+}
+
+impl<E> From<E> for AppError
+where
+    E: Into<Error>,
+{
+    fn from(err: E) -> Self {
+        Self(err.into())
+    }
+}
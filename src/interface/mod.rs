@@ -0,0 +1,4 @@
+//! Interfaces through which callers interact with the database.
+
+/// The HTTP interface for the database.
+pub mod http;
@@ -38,12 +38,25 @@
 //!
 //! ## Storage
 //!
-//! Each `metric` has its own `store` in the database. Each `key` has a "table" in the `store`.
-//! This allows for easy querying of metrics.
+//! Each `metric` has its own `store` in the database, backed by its own RocksDB column
+//! family. Each `key` has a "table" in the `store`, encoded as a key prefix within that
+//! column family. This allows for easy querying of metrics, and lets unrelated metrics be
+//! compacted, tuned, or dropped independently.
+//!
+//! ## Replication
+//!
+//! Every mutation is first appended to a persisted, append-only Raft log (see [`raft`])
+//! before being applied, so a node's own history is always durable and ordered. Cluster
+//! membership is tracked per node via `--peers`/`--node-id` and the `/cluster` routes, but
+//! there is no inter-node transport yet: each node only ever applies its own log.
 
 use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 
 use anyhow::{Error, Result};
@@ -53,20 +66,251 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
 use crate::interface::http;
+use crate::raft::{LogEntry, RaftNode};
 
 extern crate rocksdb;
 
 /// The mod that contains the interface for the database.
 mod interface;
 
+/// The mod that contains the replication scaffold for the database.
+mod raft;
+
 /// The global instance of the Metrical struct.
 static INSTANCE: OnceCell<Arc<RwLock<Metrical>>> = OnceCell::new();
 
+/// The global instance of this node's cluster membership and Raft log.
+static RAFT: OnceCell<Arc<RaftNode>> = OnceCell::new();
+
+/// The running instance of the Raft node.
+fn raft_instance() -> &'static Arc<RaftNode> {
+    RAFT.get().expect("Raft instance not initialized")
+}
+
+/// On-disk key/value format version. Bump this whenever the encoding
+/// changes so an old store can be detected and migrated at startup instead
+/// of silently misread.
+const DB_FORMAT_VERSION: u32 = 1;
+
+/// Reserved column family holding store-wide metadata, such as the format
+/// version marker.
+const META_CF: &str = "__meta__";
+
+/// Key under [`META_CF`] holding the format version the store was created
+/// with.
+const VERSION_KEY: &[u8] = b"version";
+
+/// Reserved column family holding the persisted Raft log: `index.to_be_bytes()`
+/// keys mapping to JSON-encoded [`LogEntry`] values.
+const RAFT_LOG_CF: &str = "__raft_log__";
+
+/// Whether `err` indicates on-disk corruption, as opposed to a transient or
+/// environmental failure (a lock held by another process, a permission
+/// error, a full disk, …). RocksDB's own errors aren't a typed enum in the
+/// `rocksdb` crate, so this inspects the message it prefixes corruption
+/// errors with; anything else is treated as non-corruption and propagated
+/// instead of triggering repair/rebuild, since only actual corruption
+/// justifies destructively repairing or discarding the store.
+fn is_corruption_error(err: &rocksdb::Error) -> bool {
+    let message = err.to_string();
+    message.contains("Corruption") || message.contains("corrupt")
+}
+
+/// Seconds since the Unix epoch, used to name corrupted-store backups and to
+/// evaluate retention.
+fn now_unix_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// A shared, mutable retention policy: a maximum point age in seconds, with
+/// optional per-metric overrides over a global default. Wrapped in an
+/// [`std::sync::RwLock`] (not the async `tokio` one) because it is also read
+/// from inside the synchronous RocksDB compaction filter callback.
+type SharedRetention = Arc<std::sync::RwLock<RetentionPolicy>>;
+
+/// See [`SharedRetention`].
+#[derive(Debug, Clone, Serialize)]
+struct RetentionPolicy {
+    default_secs: u64,
+    per_metric_secs: HashMap<String, u64>,
+}
+
+impl RetentionPolicy {
+    fn new(default_secs: u64) -> Self {
+        Self {
+            default_secs,
+            per_metric_secs: HashMap::new(),
+        }
+    }
+
+    fn max_age_secs(&self, metric: &str) -> u64 {
+        self.per_metric_secs
+            .get(metric)
+            .copied()
+            .unwrap_or(self.default_secs)
+    }
+}
+
+/// Build the [`rocksdb::Options`] for a metric's column family: a
+/// compaction filter that decodes the timestamp out of each key and drops
+/// any point older than that metric's retention, so expiry happens for
+/// free during normal background compaction.
+fn metric_cf_options(name: &str, retention: SharedRetention) -> rocksdb::Options {
+    let mut opts = rocksdb::Options::default();
+    let metric_name = name.to_string();
+    opts.set_compaction_filter(
+        "metrical-retention",
+        move |_level: u32, key: &[u8], _value: &[u8]| -> rocksdb::compaction_filter::Decision {
+            let (timestamp, ok) = match decode_key(key) {
+                Ok((_, pos)) => match key.get(pos..pos + 8) {
+                    Some(bytes) => (u64::from_be_bytes(bytes.try_into().unwrap()), true),
+                    None => (0, false),
+                },
+                Err(_) => (0, false),
+            };
+            if !ok {
+                return rocksdb::compaction_filter::Decision::Keep;
+            }
+
+            let max_age = retention
+                .read()
+                .expect("retention lock poisoned")
+                .max_age_secs(&metric_name);
+            if now_unix_timestamp().saturating_sub(timestamp) > max_age {
+                rocksdb::compaction_filter::Decision::Remove
+            } else {
+                rocksdb::compaction_filter::Decision::Keep
+            }
+        },
+    );
+    opts
+}
+
+/// Reserved metric name under which Metrical stores its own write/commit
+/// instrumentation, so it monitors itself through the normal metric API.
+const STATS_METRIC: &str = "__metrical_stats__";
+
+/// Whether `name` is one of the reserved column family / metric names
+/// (`default`, [`META_CF`], [`RAFT_LOG_CF`], [`STATS_METRIC`]) that callers
+/// must not be able to write to or drop as if it were an ordinary metric —
+/// doing so could corrupt the format-version marker, the persisted Raft
+/// log, or Metrical's own self-instrumentation series.
+fn is_reserved_name(name: &str) -> bool {
+    name == rocksdb::DEFAULT_COLUMN_FAMILY_NAME
+        || name == META_CF
+        || name == RAFT_LOG_CF
+        || name == STATS_METRIC
+}
+
+/// The number of most recent commit latency samples kept in memory for
+/// percentile calculation.
+const MAX_LATENCY_SAMPLES: usize = 1000;
+
+/// In-memory, thread-safe reservoir of recent commit latencies plus simple
+/// write/error counters, fed by every metric write through
+/// [`Metrical::add_metric`] and [`Metrical::add_metrics_batch`]. Raft log
+/// appends, metric drops and [`Metrical::persist_stats_points`] are not
+/// recorded.
+#[derive(Debug, Default)]
+struct WriteStats {
+    write_count: AtomicU64,
+    error_count: AtomicU64,
+    commit_latencies_us: std::sync::Mutex<VecDeque<u64>>,
+}
+
+impl WriteStats {
+    /// Record one commit attempt's latency, and whether it failed.
+    fn record(&self, elapsed: std::time::Duration, failed: bool) {
+        self.write_count.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.error_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut latencies = self
+            .commit_latencies_us
+            .lock()
+            .expect("write stats lock poisoned");
+        latencies.push_back(elapsed.as_micros() as u64);
+        if latencies.len() > MAX_LATENCY_SAMPLES {
+            latencies.pop_front();
+        }
+    }
+
+    fn snapshot(&self) -> WriteStatsSnapshot {
+        let mut sorted: Vec<u64> = self
+            .commit_latencies_us
+            .lock()
+            .expect("write stats lock poisoned")
+            .iter()
+            .copied()
+            .collect();
+        sorted.sort_unstable();
+
+        WriteStatsSnapshot {
+            write_count: self.write_count.load(Ordering::Relaxed),
+            error_count: self.error_count.load(Ordering::Relaxed),
+            commit_p50_us: percentile(&sorted, 0.50),
+            commit_p95_us: percentile(&sorted, 0.95),
+            commit_p99_us: percentile(&sorted, 0.99),
+        }
+    }
+}
+
+/// The value of `sorted[p]`-th percentile, `0.0 <= p <= 1.0`. `sorted` must
+/// already be sorted ascending.
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let index = (((sorted.len() - 1) as f64) * p).round() as usize;
+    sorted[index]
+}
+
+/// A point-in-time view of [`WriteStats`], suitable for the `/debug/stats`
+/// route and for persisting as stored series under [`STATS_METRIC`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct WriteStatsSnapshot {
+    write_count: u64,
+    error_count: u64,
+    commit_p50_us: u64,
+    commit_p95_us: u64,
+    commit_p99_us: u64,
+}
+
+impl WriteStatsSnapshot {
+    /// This snapshot as `(key, value)` pairs under [`STATS_METRIC`].
+    fn as_points(&self) -> [(&'static str, f64); 5] {
+        [
+            ("write_count", self.write_count as f64),
+            ("error_count", self.error_count as f64),
+            ("commit_p50_us", self.commit_p50_us as f64),
+            ("commit_p95_us", self.commit_p95_us as f64),
+            ("commit_p99_us", self.commit_p99_us as f64),
+        ]
+    }
+}
+
 /// # Metrical
 /// The main struct that is used to interact with the database.
+///
+/// Each metric `name` lives in its own RocksDB column family, so unrelated
+/// metrics get isolated keyspaces and compaction, and dropping a whole
+/// metric is an `O(1)` `drop_cf` rather than a scan-and-delete.
 #[derive(Debug)]
 struct Metrical {
     db: rocksdb::DB,
+    db_path: PathBuf,
+    retention: SharedRetention,
+    stats: Arc<WriteStats>,
+    next_log_index: AtomicU64,
+    /// An in-memory index of every distinct `(name, key)` series outside the
+    /// reserved names, kept up to date incrementally on every metric
+    /// write/drop instead of being rebuilt by scanning every point on every
+    /// read. See [`Self::list_series`].
+    series_index: HashMap<String, BTreeSet<String>>,
 }
 
 impl Metrical {
@@ -74,40 +318,817 @@ impl Metrical {
         INSTANCE.get().expect("Metrical instance not initialized")
     }
 
-    /// Create a new Metrical instance.
-    fn new(db_path: PathBuf) -> Result<Self, Error> {
-        let db = rocksdb::DB::open_default(db_path)?;
-        Ok(Self { db })
+    /// Create a new Metrical instance, opening every column family already
+    /// present on disk alongside the default one.
+    ///
+    /// A store that can't be opened cleanly (e.g. after an unclean shutdown)
+    /// is not fatal: this attempts [`rocksdb::DB::repair`] first, and if the
+    /// store is still unreadable, moves the damaged directory aside to a
+    /// timestamped `*.corrupt` backup and starts fresh rather than crashing.
+    fn new(db_path: PathBuf, default_retention_secs: u64) -> Result<Self, Error> {
+        let retention: SharedRetention = Arc::new(std::sync::RwLock::new(RetentionPolicy::new(
+            default_retention_secs,
+        )));
+        let db = Self::open_or_recover(&db_path, retention.clone())?;
+        Self::check_or_write_version(&db, &db_path)?;
+        let next_log_index = last_raft_log_index(&db);
+        let series_index = build_series_index(&db, &db_path)?;
+        Ok(Self {
+            db,
+            db_path,
+            retention,
+            stats: Arc::new(WriteStats::default()),
+            next_log_index: AtomicU64::new(next_log_index),
+            series_index,
+        })
+    }
+
+    fn open_or_recover(db_path: &Path, retention: SharedRetention) -> Result<rocksdb::DB, Error> {
+        let mut db_opts = rocksdb::Options::default();
+        db_opts.create_if_missing(true);
+        db_opts.create_missing_column_families(true);
+
+        let open = |retention: SharedRetention| {
+            rocksdb::DB::open_cf_descriptors(
+                &db_opts,
+                db_path,
+                Self::cf_descriptors_for(db_path, retention),
+            )
+        };
+
+        let open_err = match open(retention.clone()) {
+            Ok(db) => return Ok(db),
+            Err(e) => e,
+        };
+
+        if !is_corruption_error(&open_err) {
+            return Err(anyhow::anyhow!(
+                "failed to open database at {db_path:?}: {open_err}"
+            ));
+        }
+
+        eprintln!("Database at {db_path:?} appears corrupt ({open_err}), attempting repair");
+        if rocksdb::DB::repair(&db_opts, db_path).is_ok() {
+            if let Ok(db) = open(retention.clone()) {
+                return Ok(db);
+            }
+        }
+
+        let backup = db_path.with_file_name(format!(
+            "{}.corrupt.{}",
+            db_path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("default.db"),
+            now_unix_timestamp(),
+        ));
+        eprintln!(
+            "Database at {db_path:?} is unrecoverable, moving it aside to {backup:?} and starting fresh"
+        );
+        std::fs::rename(db_path, &backup)?;
+
+        rocksdb::DB::open_cf_descriptors(
+            &db_opts,
+            db_path,
+            vec![
+                rocksdb::ColumnFamilyDescriptor::new(
+                    rocksdb::DEFAULT_COLUMN_FAMILY_NAME,
+                    rocksdb::Options::default(),
+                ),
+                rocksdb::ColumnFamilyDescriptor::new(META_CF, rocksdb::Options::default()),
+                rocksdb::ColumnFamilyDescriptor::new(RAFT_LOG_CF, rocksdb::Options::default()),
+            ],
+        )
+        .map_err(|e| e.into())
+    }
+
+    /// The column family descriptors to open: whatever metric column
+    /// families already exist on disk (each with the retention compaction
+    /// filter installed), plus the reserved [`META_CF`] and [`RAFT_LOG_CF`]
+    /// in case this store predates them.
+    fn cf_descriptors_for(
+        db_path: &Path,
+        retention: SharedRetention,
+    ) -> Vec<rocksdb::ColumnFamilyDescriptor> {
+        let mut cf_names = rocksdb::DB::list_cf(&rocksdb::Options::default(), db_path)
+            .unwrap_or_else(|_| vec![rocksdb::DEFAULT_COLUMN_FAMILY_NAME.to_string()]);
+        if !cf_names.iter().any(|name| name == META_CF) {
+            cf_names.push(META_CF.to_string());
+        }
+        if !cf_names.iter().any(|name| name == RAFT_LOG_CF) {
+            cf_names.push(RAFT_LOG_CF.to_string());
+        }
+
+        cf_names
+            .into_iter()
+            .map(|name| {
+                let opts = if name == rocksdb::DEFAULT_COLUMN_FAMILY_NAME
+                    || name == META_CF
+                    || name == RAFT_LOG_CF
+                {
+                    rocksdb::Options::default()
+                } else {
+                    metric_cf_options(&name, retention.clone())
+                };
+                rocksdb::ColumnFamilyDescriptor::new(name, opts)
+            })
+            .collect()
+    }
+
+    /// Check the on-disk format version marker against [`DB_FORMAT_VERSION`],
+    /// writing it for the first time on a brand new store. A mismatch means
+    /// a future key-format change needs an explicit migration rather than
+    /// silently misreading old data.
+    ///
+    /// A missing marker is only treated as "brand new" if the store is
+    /// actually empty of metric data ([`Self::predates_versioning`]); a
+    /// store with data but no marker predates the marker being introduced
+    /// (e.g. the pre-per-metric-column-family or pre-binary-key layouts)
+    /// and needs an explicit migration rather than being silently adopted
+    /// as version 1.
+    fn check_or_write_version(db: &rocksdb::DB, db_path: &Path) -> Result<(), Error> {
+        let meta_cf = db
+            .cf_handle(META_CF)
+            .ok_or_else(|| anyhow::anyhow!("missing reserved '{META_CF}' column family"))?;
+
+        match db.get_cf(meta_cf, VERSION_KEY)? {
+            Some(bytes) => {
+                let stored_bytes: [u8; 4] = bytes.as_slice().try_into()?;
+                let stored_version = u32::from_be_bytes(stored_bytes);
+                if stored_version != DB_FORMAT_VERSION {
+                    anyhow::bail!(
+                        "database was created with format version {stored_version}, but this build expects version {DB_FORMAT_VERSION}; a migration is required"
+                    );
+                }
+                Ok(())
+            }
+            None => {
+                if Self::predates_versioning(db, db_path)? {
+                    anyhow::bail!(
+                        "database has data but no format version marker; it predates format version tracking and needs an explicit migration before this build can open it safely"
+                    );
+                }
+                db.put_cf(meta_cf, VERSION_KEY, DB_FORMAT_VERSION.to_be_bytes())
+                    .map_err(|e| e.into())
+            }
+        }
+    }
+
+    /// Whether a store lacking the format version marker already has data on
+    /// disk, meaning it predates the marker rather than being freshly
+    /// created: either it has metric column families besides the reserved
+    /// ones (a per-metric column family store written before the marker
+    /// existed), or the default column family itself already has entries
+    /// (the older layouts that stored every metric in the single default
+    /// column family, with string or binary keys).
+    fn predates_versioning(db: &rocksdb::DB, db_path: &Path) -> Result<bool, Error> {
+        let cf_names =
+            rocksdb::DB::list_cf(&rocksdb::Options::default(), db_path).unwrap_or_default();
+        let has_metric_cf = cf_names.iter().any(|name| {
+            name != rocksdb::DEFAULT_COLUMN_FAMILY_NAME && name != META_CF && name != RAFT_LOG_CF
+        });
+        if has_metric_cf {
+            return Ok(true);
+        }
+
+        let default_cf = db
+            .cf_handle(rocksdb::DEFAULT_COLUMN_FAMILY_NAME)
+            .ok_or_else(|| anyhow::anyhow!("missing default column family"))?;
+        Ok(db
+            .iterator_cf(default_cf, rocksdb::IteratorMode::Start)
+            .next()
+            .is_some())
+    }
+
+    /// Look up the column family for `name`, creating it first if this is
+    /// the first point ever written for that metric. New column families
+    /// get the retention compaction filter installed from the start.
+    fn ensure_cf(&mut self, name: &str) -> Result<(), Error> {
+        if self.db.cf_handle(name).is_none() {
+            self.db
+                .create_cf(name, &metric_cf_options(name, self.retention.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// Set the global default retention, applied to any metric without its
+    /// own override.
+    fn set_default_retention(&self, max_age_secs: u64) {
+        self.retention
+            .write()
+            .expect("retention lock poisoned")
+            .default_secs = max_age_secs;
+    }
+
+    /// Set a per-metric retention override.
+    fn set_metric_retention(&self, metric: String, max_age_secs: u64) {
+        self.retention
+            .write()
+            .expect("retention lock poisoned")
+            .per_metric_secs
+            .insert(metric, max_age_secs);
+    }
+
+    /// A snapshot of the current retention policy, for the admin route.
+    fn retention_policy(&self) -> RetentionPolicy {
+        self.retention.read().expect("retention lock poisoned").clone()
+    }
+
+    /// The current write/commit instrumentation, without persisting it.
+    fn stats_snapshot(&self) -> WriteStatsSnapshot {
+        self.stats.snapshot()
+    }
+
+    /// Persist an already-computed stats `snapshot` as ordinary stored points
+    /// under the reserved [`STATS_METRIC`] name, so Metrical's own ingest
+    /// health is queryable through the normal metric API.
+    ///
+    /// Written via a single [`rocksdb::WriteBatch`] that deliberately does
+    /// *not* go through [`Self::add_metrics_batch`] and feed [`WriteStats`]:
+    /// every `/debug/stats` poll would otherwise add its own write/latency
+    /// samples to the very numbers it's about to report, skewing them more
+    /// the more often the route is polled.
+    fn persist_stats_points(&mut self, snapshot: WriteStatsSnapshot) -> Result<(), Error> {
+        let timestamp = now_unix_timestamp();
+        let metrics: Vec<Metric> = snapshot
+            .as_points()
+            .into_iter()
+            .map(|(key, value)| Metric {
+                name: STATS_METRIC.to_string(),
+                key: key.to_string(),
+                timestamp,
+                value,
+            })
+            .collect();
+
+        for metric in &metrics {
+            self.ensure_cf(&metric.name)?;
+        }
+
+        let mut batch = rocksdb::WriteBatch::default();
+        for metric in &metrics {
+            let cf = self
+                .cf_handle(&metric.name)
+                .expect("column family just ensured to exist");
+            batch.put_cf(cf, encode_key(&metric.key, metric.timestamp), metric.value.to_be_bytes());
+        }
+
+        self.db.write(batch).map_err(|e| e.into())
+    }
+
+    /// Manually trigger compaction across every metric column family,
+    /// letting operators reclaim space from expired points on demand
+    /// instead of waiting for RocksDB to schedule it.
+    fn force_compact(&self) -> Result<(), Error> {
+        let cf_names = rocksdb::DB::list_cf(&rocksdb::Options::default(), &self.db_path)?;
+        for name in cf_names {
+            if name == rocksdb::DEFAULT_COLUMN_FAMILY_NAME
+                || name == META_CF
+                || name == RAFT_LOG_CF
+            {
+                continue;
+            }
+            if let Some(cf) = self.cf_handle(&name) {
+                self.db.compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>);
+            }
+        }
+        Ok(())
+    }
+
+    fn cf_handle(&self, name: &str) -> Option<&rocksdb::ColumnFamily> {
+        self.db.cf_handle(name)
     }
 
     fn add_metric(&mut self, metric: Metric) -> Result<(), Error> {
-        let key = format!("{}:{}:{}", metric.name, metric.key, metric.timestamp);
-        let value = metric.value.to_string();
-        self.db
-            .put(key.as_bytes(), value.as_bytes())
-            .map_err(|e| e.into())
+        if is_reserved_name(&metric.name) {
+            anyhow::bail!("'{}' is a reserved name and cannot be used as a metric", metric.name);
+        }
+        check_key_len(&metric.key)?;
+        self.ensure_cf(&metric.name)?;
+        let cf = self
+            .cf_handle(&metric.name)
+            .expect("column family just ensured to exist");
+        let key = encode_key(&metric.key, metric.timestamp);
+
+        let started_at = std::time::Instant::now();
+        let result = self.db.put_cf(cf, key, metric.value.to_be_bytes());
+        self.stats.record(started_at.elapsed(), result.is_err());
+
+        if result.is_ok() {
+            self.series_index
+                .entry(metric.name)
+                .or_default()
+                .insert(metric.key);
+        }
+
+        result.map_err(|e| e.into())
     }
 
     fn get_metrics(&mut self, name: &str, key: &str) -> Result<Vec<Metric>, Error> {
-        let prefix = format!("{}:{}:", name, key);
-        let iter = self.db.prefix_iterator(prefix.as_bytes());
+        let Some(cf) = self.cf_handle(name) else {
+            return Ok(Vec::new());
+        };
+        let prefix = encode_prefix(key);
+        let iter = self.db.prefix_iterator_cf(cf, &prefix);
         let mut metrics = Vec::new();
         for result in iter {
             let (key, value) = result?;
-            let key = std::str::from_utf8(&key)?;
-            let value = std::str::from_utf8(&value)?;
-            let parts: Vec<&str> = key.split(':').collect();
-            let timestamp = parts[2].parse::<u64>()?;
-            let value = value.parse::<f64>()?;
-            metrics.push(Metric {
-                name: parts[0].to_string(),
-                key: parts[1].to_string(),
-                timestamp,
-                value,
-            });
+            if !key.starts_with(&prefix[..]) {
+                break;
+            }
+            metrics.push(decode_metric(name, &key, &value)?);
         }
         Ok(metrics)
     }
+
+    /// Fetch every point for `name`/`key` with `start_ts <= timestamp < end_ts`.
+    ///
+    /// The keys are laid out so that the timestamp suffix sorts in true
+    /// chronological order, which lets this seek straight to `start_ts` and
+    /// stop at `end_ts` instead of scanning the whole series.
+    fn get_metrics_range(
+        &mut self,
+        name: &str,
+        key: &str,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Result<Vec<Metric>, Error> {
+        let Some(cf) = self.cf_handle(name) else {
+            return Ok(Vec::new());
+        };
+        let prefix = encode_prefix(key);
+        let start_key = encode_key(key, start_ts);
+        let end_key = encode_key(key, end_ts);
+
+        let mut opts = rocksdb::ReadOptions::default();
+        opts.set_iterate_upper_bound(end_key);
+
+        let iter = self.db.raw_iterator_cf_opt(cf, opts);
+        read_range_from(name, iter, &prefix, &start_key)
+    }
+
+    /// Write every metric in `metrics` through a single [`rocksdb::WriteBatch`],
+    /// so that either all of them land or none do.
+    fn add_metrics_batch(&mut self, metrics: &[Metric]) -> Result<(), Error> {
+        for metric in metrics {
+            if is_reserved_name(&metric.name) {
+                anyhow::bail!(
+                    "'{}' is a reserved name and cannot be used as a metric",
+                    metric.name
+                );
+            }
+            check_key_len(&metric.key)?;
+            self.ensure_cf(&metric.name)?;
+        }
+
+        let mut batch = rocksdb::WriteBatch::default();
+        for metric in metrics {
+            let cf = self
+                .cf_handle(&metric.name)
+                .expect("column family just ensured to exist");
+            let key = encode_key(&metric.key, metric.timestamp);
+            batch.put_cf(cf, key, metric.value.to_be_bytes());
+        }
+
+        let started_at = std::time::Instant::now();
+        let result = self.db.write(batch);
+        self.stats.record(started_at.elapsed(), result.is_err());
+
+        if result.is_ok() {
+            for metric in metrics {
+                self.series_index
+                    .entry(metric.name.clone())
+                    .or_default()
+                    .insert(metric.key.clone());
+            }
+        }
+
+        result.map_err(|e| e.into())
+    }
+
+    /// Resolve every selector against a single [`rocksdb::DB::snapshot`], so
+    /// all series are read at the same consistent point in time.
+    fn get_metrics_batch(
+        &self,
+        selectors: &[MetricSelector],
+    ) -> Result<Vec<(MetricSelector, Vec<Metric>)>, Error> {
+        let snapshot = self.db.snapshot();
+        let mut results = Vec::with_capacity(selectors.len());
+        for selector in selectors {
+            let metrics = match self.cf_handle(&selector.name) {
+                Some(cf) => {
+                    let prefix = encode_prefix(&selector.key);
+                    match selector.range {
+                        Some((start_ts, end_ts)) => {
+                            let start_key = encode_key(&selector.key, start_ts);
+                            let end_key = encode_key(&selector.key, end_ts);
+                            let mut opts = rocksdb::ReadOptions::default();
+                            opts.set_iterate_upper_bound(end_key);
+                            let iter = snapshot.raw_iterator_cf_opt(cf, opts);
+                            read_range_from(&selector.name, iter, &prefix, &start_key)?
+                        }
+                        None => {
+                            let iter = snapshot.raw_iterator_cf(cf);
+                            read_range_from(&selector.name, iter, &prefix, &prefix)?
+                        }
+                    }
+                }
+                None => Vec::new(),
+            };
+            results.push((selector.clone(), metrics));
+        }
+        Ok(results)
+    }
+
+    /// List every distinct `(name, key)` series currently stored, `name`
+    /// being a column family and `key` a distinct key within it. Series
+    /// under the reserved [`STATS_METRIC`] name are not listed.
+    ///
+    /// Served entirely from [`Self::series_index`], which is kept up to
+    /// date incrementally on every write/drop, rather than scanning every
+    /// point in every metric column family — a scan that `/metrics` would
+    /// otherwise repeat on every Prometheus scrape.
+    fn list_series(&self) -> Vec<(String, String)> {
+        self.series_index
+            .iter()
+            .flat_map(|(name, keys)| keys.iter().map(move |key| (name.clone(), key.clone())))
+            .collect()
+    }
+
+    /// Roll `name`/`key` up into fixed-size `bucket_secs` windows over
+    /// `[start_ts, end_ts)`, aggregating each window with `agg`.
+    ///
+    /// This is a single streaming pass over the (chronologically ordered)
+    /// range: it keeps a running accumulator for the current bucket and
+    /// finalizes it into one output [`Metric`], stamped at the bucket's
+    /// start time, whenever the iterator crosses into the next bucket. This
+    /// keeps memory `O(1)` regardless of the range size.
+    fn get_metrics_aggregated(
+        &mut self,
+        name: &str,
+        key: &str,
+        start_ts: u64,
+        end_ts: u64,
+        bucket_secs: u64,
+        agg: Aggregate,
+    ) -> Result<Vec<Metric>, Error> {
+        if bucket_secs == 0 {
+            anyhow::bail!("bucket_secs must be greater than zero");
+        }
+        let Some(cf) = self.cf_handle(name) else {
+            return Ok(Vec::new());
+        };
+
+        let prefix = encode_prefix(key);
+        let start_key = encode_key(key, start_ts);
+        let end_key = encode_key(key, end_ts);
+
+        let mut opts = rocksdb::ReadOptions::default();
+        opts.set_iterate_upper_bound(end_key);
+        let mut iter = self.db.raw_iterator_cf_opt(cf, opts);
+        iter.seek(&start_key);
+
+        let mut rollups = Vec::new();
+        let mut bucket = BucketAccumulator::new();
+
+        while iter.valid() {
+            let k = iter.key().ok_or_else(|| anyhow::anyhow!("iterator key missing"))?;
+            if !k.starts_with(&prefix[..]) {
+                break;
+            }
+            let v = iter
+                .value()
+                .ok_or_else(|| anyhow::anyhow!("iterator value missing"))?;
+            let metric = decode_metric(name, k, v)?;
+            let bucket_start = metric.timestamp / bucket_secs * bucket_secs;
+
+            if let Some(finished) = bucket.push(bucket_start, metric.value) {
+                rollups.push(finished.into_metric(name, key, agg));
+            }
+
+            iter.next();
+        }
+        if let Some(finished) = bucket.finish() {
+            rollups.push(finished.into_metric(name, key, agg));
+        }
+
+        Ok(rollups)
+    }
+
+    /// Fetch the most recent point for `name`/`key`, if any.
+    ///
+    /// Seeks straight to the series' upper-bound key and steps backward,
+    /// which pairs naturally with the big-endian timestamp suffix.
+    fn get_latest(&self, name: &str, key: &str) -> Result<Option<Metric>, Error> {
+        let Some(cf) = self.cf_handle(name) else {
+            return Ok(None);
+        };
+        let prefix = encode_prefix(key);
+        let upper_key = encode_key(key, u64::MAX);
+
+        let mut iter = self.db.raw_iterator_cf(cf);
+        iter.seek_for_prev(&upper_key);
+        if !iter.valid() {
+            return Ok(None);
+        }
+        let found_key = iter.key().ok_or_else(|| anyhow::anyhow!("iterator key missing"))?;
+        if !found_key.starts_with(&prefix[..]) {
+            return Ok(None);
+        }
+        let value = iter
+            .value()
+            .ok_or_else(|| anyhow::anyhow!("iterator value missing"))?;
+        Ok(Some(decode_metric(name, found_key, value)?))
+    }
+
+    /// Append `entry` to the persisted Raft log under [`RAFT_LOG_CF`],
+    /// keyed by a monotonically increasing big-endian index, and return
+    /// that index. Called by [`crate::raft::RaftNode::propose`] before
+    /// applying the entry, so the log is always ahead of (or equal to)
+    /// applied state.
+    fn append_raft_log(&mut self, entry: &LogEntry) -> Result<u64, Error> {
+        let cf = self
+            .cf_handle(RAFT_LOG_CF)
+            .ok_or_else(|| anyhow::anyhow!("missing reserved '{RAFT_LOG_CF}' column family"))?;
+        let index = self.next_log_index.fetch_add(1, Ordering::Relaxed);
+        let value = serde_json::to_vec(entry)?;
+        self.db.put_cf(cf, index.to_be_bytes(), value)?;
+        Ok(index)
+    }
+
+    /// Drop a metric and its entire column family in `O(1)`, rather than
+    /// scanning and deleting every point.
+    fn drop_metric(&mut self, name: &str) -> Result<(), Error> {
+        if is_reserved_name(name) {
+            anyhow::bail!("'{name}' is a reserved name and cannot be dropped");
+        }
+        if self.db.cf_handle(name).is_some() {
+            self.db.drop_cf(name)?;
+        }
+        self.series_index.remove(name);
+        Ok(())
+    }
+}
+
+/// Build the initial `(name -> keys)` series index by scanning every metric
+/// column family once at startup. Ongoing writes keep the index up to date
+/// incrementally from here on, so this full scan only happens once per
+/// process lifetime rather than on every `/metrics` scrape.
+fn build_series_index(
+    db: &rocksdb::DB,
+    db_path: &Path,
+) -> Result<HashMap<String, BTreeSet<String>>, Error> {
+    let cf_names = rocksdb::DB::list_cf(&rocksdb::Options::default(), db_path)?;
+    let mut index: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for name in cf_names {
+        if is_reserved_name(&name) {
+            continue;
+        }
+        let Some(cf) = db.cf_handle(&name) else {
+            continue;
+        };
+        let keys = index.entry(name).or_default();
+        for result in db.iterator_cf(cf, rocksdb::IteratorMode::Start) {
+            let (key, _) = result?;
+            let (key_str, _) = decode_key(&key)?;
+            keys.insert(key_str);
+        }
+    }
+    Ok(index)
+}
+
+/// The index the next Raft log entry should be written at: one past the
+/// highest index already persisted under [`RAFT_LOG_CF`], or `0` for a
+/// brand new store.
+fn last_raft_log_index(db: &rocksdb::DB) -> u64 {
+    let Some(cf) = db.cf_handle(RAFT_LOG_CF) else {
+        return 0;
+    };
+    let mut iter = db.raw_iterator_cf(cf);
+    iter.seek_to_last();
+    if !iter.valid() {
+        return 0;
+    }
+    match iter.key() {
+        Some(key) if key.len() == 8 => u64::from_be_bytes(key.try_into().unwrap()) + 1,
+        _ => 0,
+    }
+}
+
+/// Drain a raw iterator, already seeked or about to be seeked to `start_key`,
+/// for as long as its keys fall under `prefix`. Shared by single-series range
+/// reads and the batch read path, which iterates over a [`rocksdb::Snapshot`]
+/// instead of the `DB` directly.
+fn read_range_from(
+    name: &str,
+    mut iter: rocksdb::DBRawIterator<'_>,
+    prefix: &[u8],
+    start_key: &[u8],
+) -> Result<Vec<Metric>, Error> {
+    iter.seek(start_key);
+    let mut metrics = Vec::new();
+    while iter.valid() {
+        let key = iter.key().ok_or_else(|| anyhow::anyhow!("iterator key missing"))?;
+        if !key.starts_with(prefix) {
+            break;
+        }
+        let value = iter
+            .value()
+            .ok_or_else(|| anyhow::anyhow!("iterator value missing"))?;
+        metrics.push(decode_metric(name, key, value)?);
+        iter.next();
+    }
+    Ok(metrics)
+}
+
+/// The aggregation applied to each bucket by [`Metrical::get_metrics_aggregated`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Aggregate {
+    /// The smallest value in the bucket.
+    Min,
+    /// The largest value in the bucket.
+    Max,
+    /// The sum of every value in the bucket.
+    Sum,
+    /// The mean of every value in the bucket.
+    Avg,
+    /// The number of points in the bucket.
+    Count,
+    /// The value of the last point in the bucket.
+    Last,
+}
+
+/// Running state for the bucket currently being accumulated by
+/// [`Metrical::get_metrics_aggregated`]. Tracks just enough to compute any
+/// [`Aggregate`] once the bucket is finalized, independent of how many
+/// points land in it.
+struct BucketAccumulator {
+    current: Option<u64>,
+    sum: f64,
+    count: u64,
+    min: f64,
+    max: f64,
+    last: f64,
+}
+
+/// A finalized bucket, ready to be turned into an output [`Metric`].
+struct FinishedBucket {
+    bucket_start: u64,
+    sum: f64,
+    count: u64,
+    min: f64,
+    max: f64,
+    last: f64,
+}
+
+impl BucketAccumulator {
+    fn new() -> Self {
+        Self {
+            current: None,
+            sum: 0.0,
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            last: 0.0,
+        }
+    }
+
+    /// Fold `value` into the bucket starting at `bucket_start`, finalizing
+    /// and returning the previous bucket first if this one has moved on.
+    fn push(&mut self, bucket_start: u64, value: f64) -> Option<FinishedBucket> {
+        let finished = if self.current.is_some() && self.current != Some(bucket_start) {
+            let finished = self.finish();
+            *self = Self::new();
+            finished
+        } else {
+            None
+        };
+
+        self.current = Some(bucket_start);
+        self.sum += value;
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.last = value;
+
+        finished
+    }
+
+    /// Finalize whatever bucket is currently accumulating, if any.
+    fn finish(&self) -> Option<FinishedBucket> {
+        self.current.map(|bucket_start| FinishedBucket {
+            bucket_start,
+            sum: self.sum,
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            last: self.last,
+        })
+    }
+}
+
+impl FinishedBucket {
+    fn into_metric(self, name: &str, key: &str, agg: Aggregate) -> Metric {
+        let value = match agg {
+            Aggregate::Min => self.min,
+            Aggregate::Max => self.max,
+            Aggregate::Sum => self.sum,
+            Aggregate::Avg => self.sum / self.count as f64,
+            Aggregate::Count => self.count as f64,
+            Aggregate::Last => self.last,
+        };
+        Metric {
+            name: name.to_string(),
+            key: key.to_string(),
+            timestamp: self.bucket_start,
+            value,
+        }
+    }
+}
+
+/// A single series selector used by the batch query endpoint: which
+/// `name`/`key` to read, and an optional `(start_ts, end_ts)` time range.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct MetricSelector {
+    name: String,
+    key: String,
+    range: Option<(u64, u64)>,
+}
+
+/// The largest `key` that can be encoded: a 2-byte big-endian length prefix
+/// covers up to `u16::MAX` bytes. [`add_metric`](Metrical::add_metric) and
+/// [`add_metrics_batch`](Metrical::add_metrics_batch) reject anything longer
+/// up front, rather than truncating the length prefix and silently corrupting
+/// both that key and whatever else collides with it.
+const MAX_KEY_LEN: usize = u16::MAX as usize;
+
+/// Reject `key`s too long for [`encode_prefix`]/[`encode_key`] to represent
+/// without truncating their length prefix.
+fn check_key_len(key: &str) -> Result<(), Error> {
+    if key.len() > MAX_KEY_LEN {
+        anyhow::bail!("metric key is {} bytes, exceeding the {MAX_KEY_LEN}-byte maximum", key.len());
+    }
+    Ok(())
+}
+
+/// Encode the `key` portion of a storage key, shared by point keys and the
+/// prefix used to scan a series. `name` is no longer part of the key itself
+/// since it now selects the column family instead.
+///
+/// The component is length-prefixed with 2 big-endian bytes (see
+/// [`MAX_KEY_LEN`]) so that a `key` containing `:` (or any other byte) can
+/// never be misparsed the way the old `"{name}:{key}:{timestamp}"` string
+/// format could. Callers must have already rejected keys longer than
+/// [`MAX_KEY_LEN`] via [`check_key_len`]; this function truncates silently
+/// otherwise.
+fn encode_prefix(key: &str) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(2 + key.len());
+    buf.extend_from_slice(&(key.len() as u16).to_be_bytes());
+    buf.extend_from_slice(key.as_bytes());
+    buf
+}
+
+/// Encode a full storage key: `len(key) || key || timestamp.to_be_bytes()`.
+///
+/// The timestamp is stored as 8 big-endian bytes, which sorts in true
+/// chronological order under RocksDB's default byte comparator.
+fn encode_key(key: &str, timestamp: u64) -> Vec<u8> {
+    let mut buf = encode_prefix(key);
+    buf.extend_from_slice(&timestamp.to_be_bytes());
+    buf
+}
+
+/// Decode the `key` portion of a storage key, returning it along with the
+/// offset of the first byte of the timestamp suffix.
+fn decode_key(key: &[u8]) -> Result<(String, usize), Error> {
+    let len_bytes: [u8; 2] = key
+        .get(0..2)
+        .ok_or_else(|| anyhow::anyhow!("key too short: missing key length"))?
+        .try_into()?;
+    let key_len = u16::from_be_bytes(len_bytes) as usize;
+    let key_str = std::str::from_utf8(&key[2..2 + key_len])?.to_string();
+    Ok((key_str, 2 + key_len))
+}
+
+/// Decode a stored `(key, value)` pair back into a [`Metric`]. `name` comes
+/// from the column family the pair was read from, since it is no longer
+/// encoded in the key.
+fn decode_metric(name: &str, key: &[u8], value: &[u8]) -> Result<Metric, Error> {
+    let (key_str, pos) = decode_key(key)?;
+
+    let timestamp_bytes: [u8; 8] = key[pos..pos + 8].try_into()?;
+    let timestamp = u64::from_be_bytes(timestamp_bytes);
+
+    let value_bytes: [u8; 8] = value.try_into()?;
+    let value = f64::from_be_bytes(value_bytes);
+
+    Ok(Metric {
+        name: name.to_string(),
+        key: key_str,
+        timestamp,
+        value,
+    })
 }
 
 /// # Metric
@@ -126,6 +1147,21 @@ struct Args {
     /// The path to the database.
     #[clap(long, default_value = "/etc/metrical/default.db")]
     db_path: PathBuf,
+
+    /// The default maximum age, in seconds, a point is retained for before
+    /// it becomes eligible for removal during compaction. Overridable per
+    /// metric via the `/retention` route.
+    #[clap(long, default_value = "2592000")]
+    default_retention_secs: u64,
+
+    /// This node's id within its Raft cluster.
+    #[clap(long, default_value = "1")]
+    node_id: u64,
+
+    /// Comma-separated addresses of the other nodes in this cluster, known
+    /// at startup. More peers can be added later via `/cluster/join`.
+    #[clap(long, default_value = "")]
+    peers: String,
 }
 
 fn create_db_dir(db: &Path) -> Result<(), Error> {
@@ -157,10 +1193,145 @@ async fn main() -> Result<(), Error> {
     println!("Opening database at: {:?}", args.db_path);
     create_db_dir(&args.db_path)?;
     INSTANCE
-        .set(Arc::new(RwLock::new(Metrical::new(args.db_path)?)))
+        .set(Arc::new(RwLock::new(Metrical::new(
+            args.db_path,
+            args.default_retention_secs,
+        )?)))
         .map_err(|_| anyhow::anyhow!("Failed to set Metrical instance"))?;
 
+    let peers = args
+        .peers
+        .split(',')
+        .map(str::trim)
+        .filter(|addr| !addr.is_empty())
+        .map(str::to_string)
+        .collect();
+    RAFT.set(Arc::new(RaftNode::new(args.node_id, peers)))
+        .map_err(|_| anyhow::anyhow!("Failed to set Raft instance"))?;
+
     http::serve().await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_round_trips_through_encode_and_decode() {
+        let key = "backend-server1";
+        let timestamp = 1_700_000_000;
+        let encoded = encode_key(key, timestamp);
+        let (decoded_key, pos) = decode_key(&encoded).unwrap();
+        assert_eq!(decoded_key, key);
+        assert_eq!(&encoded[pos..], &timestamp.to_be_bytes());
+    }
+
+    #[test]
+    fn key_containing_colon_round_trips() {
+        let key = "db:server1:replica";
+        let encoded = encode_key(key, 42);
+        let (decoded_key, _) = decode_key(&encoded).unwrap();
+        assert_eq!(decoded_key, key);
+    }
+
+    #[test]
+    fn decode_metric_round_trips_value_and_timestamp() {
+        let metric = Metric {
+            name: "cpu".to_string(),
+            key: "backend-server1".to_string(),
+            timestamp: 1_700_000_000,
+            value: 0.532,
+        };
+        let encoded_key = encode_key(&metric.key, metric.timestamp);
+        let decoded = decode_metric(
+            &metric.name,
+            &encoded_key,
+            &metric.value.to_be_bytes(),
+        )
+        .unwrap();
+        assert_eq!(decoded, metric);
+    }
+
+    #[test]
+    fn encode_prefix_is_a_prefix_of_encode_key() {
+        let key = "backend-server1";
+        let prefix = encode_prefix(key);
+        let full = encode_key(key, 1_700_000_000);
+        assert!(full.starts_with(&prefix[..]));
+    }
+
+    #[test]
+    fn key_at_max_len_round_trips_without_truncation() {
+        let key = "k".repeat(MAX_KEY_LEN);
+        assert!(check_key_len(&key).is_ok());
+        let encoded = encode_key(&key, 1);
+        let (decoded_key, _) = decode_key(&encoded).unwrap();
+        assert_eq!(decoded_key.len(), MAX_KEY_LEN);
+    }
+
+    #[test]
+    fn check_key_len_rejects_keys_past_the_u16_length_prefix() {
+        let key = "k".repeat(MAX_KEY_LEN + 1);
+        assert!(check_key_len(&key).is_err());
+    }
+
+    #[test]
+    fn percentile_of_empty_slice_is_zero() {
+        assert_eq!(percentile(&[], 0.50), 0);
+    }
+
+    #[test]
+    fn percentile_of_single_value_is_that_value() {
+        assert_eq!(percentile(&[42], 0.99), 42);
+    }
+
+    #[test]
+    fn percentile_picks_expected_rank_from_sorted_samples() {
+        let sorted: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&sorted, 0.0), 1);
+        assert_eq!(percentile(&sorted, 0.50), 51);
+        assert_eq!(percentile(&sorted, 1.0), 100);
+    }
+
+    #[test]
+    fn bucket_accumulator_computes_every_aggregate_within_one_bucket() {
+        let mut bucket = BucketAccumulator::new();
+        assert!(bucket.push(0, 1.0).is_none());
+        assert!(bucket.push(0, 2.0).is_none());
+        assert!(bucket.push(0, 3.0).is_none());
+
+        let finished = bucket.finish().unwrap();
+        assert_eq!(finished.min, 1.0);
+        assert_eq!(finished.max, 3.0);
+        assert_eq!(finished.sum, 6.0);
+        assert_eq!(finished.count, 3);
+        assert_eq!(finished.last, 3.0);
+
+        let metric = finished.into_metric("cpu", "backend-server1", Aggregate::Avg);
+        assert_eq!(metric.value, 2.0);
+    }
+
+    #[test]
+    fn bucket_accumulator_finalizes_previous_bucket_on_boundary_crossing() {
+        let mut bucket = BucketAccumulator::new();
+        assert!(bucket.push(0, 1.0).is_none());
+        assert!(bucket.push(0, 3.0).is_none());
+
+        let finished = bucket.push(60, 10.0).expect("bucket boundary crossed");
+        assert_eq!(finished.bucket_start, 0);
+        assert_eq!(finished.count, 2);
+        assert_eq!(finished.sum, 4.0);
+
+        let second = bucket.finish().unwrap();
+        assert_eq!(second.bucket_start, 60);
+        assert_eq!(second.count, 1);
+        assert_eq!(second.last, 10.0);
+    }
+
+    #[test]
+    fn bucket_accumulator_finish_on_empty_bucket_is_none() {
+        assert!(BucketAccumulator::new().finish().is_none());
+    }
+}
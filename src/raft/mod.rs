@@ -0,0 +1,122 @@
+//! A minimal Raft-flavored replication scaffold for Metrical.
+//!
+//! This wires up the pieces a real consensus implementation would sit
+//! behind: a log entry enum covering every mutating operation, a
+//! persisted append-only log (in its own column family, alongside the
+//! metric data), and a single apply path (`RaftNode::propose`) that is the
+//! only caller of `Metrical`'s write methods.
+//!
+//! What is **not** implemented yet is the network side of Raft itself:
+//! leader election and log replication RPCs to `peers`. `propose` commits
+//! to the local log and applies locally; it does not replicate anywhere.
+//! Wiring an actual transport (e.g. implementing `openraft`'s
+//! `RaftNetwork`/`RaftStorage` traits against the types here) is the next
+//! step before `--peers`/`--node-id` affect anything over the wire.
+
+use std::sync::{Arc, RwLock as StdRwLock};
+
+use anyhow::{Error, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{Metric, Metrical, WriteStatsSnapshot};
+
+/// A single mutating operation, persisted to the Raft log before being
+/// applied. [`RaftNode::propose`] is the only path that may call into
+/// `Metrical`'s write methods, so every mutation is durably logged first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LogEntry {
+    /// Ingest a single point.
+    AddMetric(Metric),
+    /// Ingest a batch of points atomically.
+    BatchAdd(Vec<Metric>),
+    /// Drop an entire metric and its column family.
+    DropMetric(String),
+    /// Persist an already-computed write/commit stats snapshot as stored
+    /// points. Kept distinct from `BatchAdd` because it must not feed
+    /// `WriteStats` itself (see `Metrical::persist_stats_points`).
+    PersistStats(WriteStatsSnapshot),
+}
+
+/// How a read should be served relative to cluster state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsistencyLevel {
+    /// Serve from local state without checking leadership.
+    Eventual,
+    /// Forward to the leader so the read reflects every committed write.
+    /// Not yet implemented — there is no inter-node transport yet, so this
+    /// currently behaves exactly like `Eventual`.
+    Strong,
+}
+
+/// Cluster identity and (locally known) membership for this node.
+#[derive(Debug)]
+pub struct RaftNode {
+    node_id: u64,
+    peers: StdRwLock<Vec<String>>,
+}
+
+/// A snapshot of cluster membership, for the `/cluster` status route.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterStatus {
+    /// This node's id.
+    pub node_id: u64,
+    /// The peers this node currently knows about.
+    pub peers: Vec<String>,
+}
+
+impl RaftNode {
+    /// Create a node identified by `node_id`, starting with `peers` as its
+    /// initial membership list.
+    pub fn new(node_id: u64, peers: Vec<String>) -> Self {
+        Self {
+            node_id,
+            peers: StdRwLock::new(peers),
+        }
+    }
+
+    /// A snapshot of this node's id and locally known peers.
+    pub fn status(&self) -> ClusterStatus {
+        ClusterStatus {
+            node_id: self.node_id,
+            peers: self.peers.read().expect("peers lock poisoned").clone(),
+        }
+    }
+
+    /// Add `addr` to the locally known membership list.
+    pub fn join(&self, addr: String) {
+        let mut peers = self.peers.write().expect("peers lock poisoned");
+        if !peers.contains(&addr) {
+            peers.push(addr);
+        }
+    }
+
+    /// Remove `addr` from the locally known membership list.
+    pub fn leave(&self, addr: &str) {
+        self.peers
+            .write()
+            .expect("peers lock poisoned")
+            .retain(|peer| peer != addr);
+    }
+
+    /// Append `entry` to the persisted Raft log and apply it to `metrical`.
+    ///
+    /// Every mutation must go through here rather than calling `Metrical`'s
+    /// write methods directly, so the log is always ahead of (or equal to)
+    /// applied state.
+    pub async fn propose(
+        &self,
+        metrical: &Arc<RwLock<Metrical>>,
+        entry: LogEntry,
+    ) -> Result<(), Error> {
+        let mut metrical = metrical.write().await;
+        metrical.append_raft_log(&entry)?;
+        match entry {
+            LogEntry::AddMetric(metric) => metrical.add_metric(metric),
+            LogEntry::BatchAdd(metrics) => metrical.add_metrics_batch(&metrics),
+            LogEntry::DropMetric(name) => metrical.drop_metric(&name),
+            LogEntry::PersistStats(snapshot) => metrical.persist_stats_points(snapshot),
+        }
+    }
+}